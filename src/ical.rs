@@ -0,0 +1,294 @@
+// --- RFC 5545 VTODO <-> Task mapping ---
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result, bail};
+use time::macros::format_description;
+use uuid::Uuid;
+
+use crate::model::{Status, Task, TimeStamp};
+
+const ICAL_TIME: &[time::format_description::FormatItem] =
+    format_description!("[year][month][day]T[hour][minute][second]");
+
+/// X-prop used to round-trip our own task id through import/export.
+const X_UID_PROP: &str = "X-TODO-UUID";
+
+fn format_ical_time(ts: TimeStamp) -> String {
+    format!(
+        "{}Z",
+        ts.to_offset(time::UtcOffset::UTC)
+            .format(ICAL_TIME)
+            .expect("formatting a valid TimeStamp should never fail")
+    )
+}
+
+fn parse_ical_time(s: &str) -> Result<TimeStamp> {
+    let trimmed = s.trim_end_matches('Z');
+    let pdt = time::PrimitiveDateTime::parse(trimmed, ICAL_TIME)
+        .with_context(|| format!("parsing iCal timestamp {s}"))?;
+    Ok(pdt.assume_utc())
+}
+
+/// Our 0 (unprioritized) .. 5 (lowest) scale -> iCal's 0 (undefined) .. 9 (lowest).
+fn priority_to_ical(p: u8) -> u8 {
+    if p == 0 { 0 } else { 1 + (p.min(5) - 1) * 2 }
+}
+
+fn priority_from_ical(p: u8) -> u8 {
+    if p == 0 { 0 } else { ((p.saturating_sub(1)) / 2) + 1 }
+}
+
+fn status_to_ical(status: Status) -> &'static str {
+    match status {
+        Status::Pending => "NEEDS-ACTION",
+        Status::InProgress => "IN-PROCESS",
+        Status::Done => "COMPLETED",
+        Status::Canceled => "CANCELLED",
+    }
+}
+
+fn status_from_ical(s: &str) -> Status {
+    match s {
+        "IN-PROCESS" => Status::InProgress,
+        "COMPLETED" => Status::Done,
+        "CANCELLED" => Status::Canceled,
+        _ => Status::Pending,
+    }
+}
+
+/// Escape text per RFC 5545 §3.3.11 (backslash, semicolon, comma, newline).
+fn escape_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(s: &str) -> String {
+    s.replace("\\n", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// Split on `sep`, treating a backslash-escaped separator (`\,`) as literal
+/// text rather than a delimiter. Each returned piece is still raw/escaped.
+fn split_unescaped(s: &str, sep: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            current.push(c);
+            if let Some(next) = chars.next() {
+                current.push(next);
+            }
+        } else if c == sep {
+            parts.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+/// Render a single task as a VTODO component.
+fn task_to_vtodo(task: &Task) -> String {
+    let mut lines = vec!["BEGIN:VTODO".to_string()];
+
+    lines.push(format!("UID:{}", task.id));
+    lines.push(format!("{X_UID_PROP}:{}", task.id));
+    lines.push(format!("SUMMARY:{}", escape_text(&task.title)));
+    if let Some(desc) = &task.desc {
+        lines.push(format!("DESCRIPTION:{}", escape_text(desc)));
+    }
+    lines.push(format!("CREATED:{}", format_ical_time(task.created_at)));
+    lines.push(format!("DTSTAMP:{}", format_ical_time(task.created_at)));
+    if let Some(completed_at) = task.completed_at {
+        lines.push(format!("COMPLETED:{}", format_ical_time(completed_at)));
+    }
+    if !task.tags.is_empty() {
+        let tags = task
+            .tags
+            .iter()
+            .map(|t| escape_text(t))
+            .collect::<Vec<_>>()
+            .join(",");
+        lines.push(format!("CATEGORIES:{tags}"));
+    }
+    lines.push(format!("PRIORITY:{}", priority_to_ical(task.priority)));
+    lines.push(format!("STATUS:{}", status_to_ical(task.status)));
+    lines.push("END:VTODO".to_string());
+
+    lines.join("\r\n")
+}
+
+/// Render every task as a VCALENDAR of VTODOs.
+pub fn export_tasks(tasks: &[Task]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//todo-cli//ical export//EN".to_string(),
+    ];
+    for task in tasks {
+        lines.push(task_to_vtodo(task));
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.join("\r\n") + "\r\n"
+}
+
+/// Unfold continuation lines (a leading space or tab joins to the previous line).
+fn unfold(ics: &str) -> String {
+    let mut out = String::with_capacity(ics.len());
+    for line in ics.split("\r\n").flat_map(|l| l.split('\n')) {
+        if (line.starts_with(' ') || line.starts_with('\t')) && !out.is_empty() {
+            out.push_str(&line[1..]);
+        } else {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+/// Parse a VCALENDAR's VTODO components into fresh [`Task`]s.
+/// Each VTODO keeps the id in our own `X-TODO-UUID` property, or else its
+/// `UID` if that parses as a UUID; otherwise it gets a fresh id.
+pub fn import_tasks(ics: &str) -> Result<Vec<Task>> {
+    let unfolded = unfold(ics);
+    let mut tasks = Vec::new();
+    let mut current: Option<Vec<(String, String)>> = None;
+
+    for line in unfolded.lines() {
+        if line == "BEGIN:VTODO" {
+            current = Some(Vec::new());
+            continue;
+        }
+        if line == "END:VTODO" {
+            let props = current.take().context("END:VTODO without BEGIN:VTODO")?;
+            tasks.push(vtodo_to_task(&props)?);
+            continue;
+        }
+        if let Some(props) = current.as_mut() {
+            if let Some((key, value)) = line.split_once(':') {
+                // Strip any `;PARAM=...` suffix on the property name.
+                let key = key.split(';').next().unwrap_or(key);
+                // Keep the raw (still-escaped) value: some properties (e.g.
+                // CATEGORIES) need to split on a delimiter before unescaping,
+                // since unescaping first would make `\,` indistinguishable
+                // from a real separator.
+                props.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    Ok(tasks)
+}
+
+fn vtodo_to_task(props: &[(String, String)]) -> Result<Task> {
+    let get = |name: &str| props.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+
+    let id = get(X_UID_PROP)
+        .or_else(|| get("UID"))
+        .and_then(|v| Uuid::parse_str(v).ok())
+        .unwrap_or_else(Uuid::new_v4);
+
+    let title = unescape_text(get("SUMMARY").context("VTODO missing SUMMARY")?);
+    let desc = get("DESCRIPTION").map(unescape_text);
+
+    let created_at = match get("CREATED").or_else(|| get("DTSTAMP")) {
+        Some(v) => parse_ical_time(v)?,
+        None => TimeStamp::now_utc(),
+    };
+    let completed_at = get("COMPLETED").map(parse_ical_time).transpose()?;
+
+    let tags: Vec<String> = match get("CATEGORIES") {
+        Some(v) => split_unescaped(v, ',')
+            .iter()
+            .map(|t| unescape_text(t))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let priority = match get("PRIORITY") {
+        Some(v) => priority_from_ical(v.parse().unwrap_or(0)),
+        None => 0,
+    };
+
+    let status = get("STATUS").map(|s| status_from_ical(s)).unwrap_or(Status::Pending);
+    if status == Status::Done && completed_at.is_none() {
+        bail!("VTODO {title} is COMPLETED but has no COMPLETED timestamp");
+    }
+
+    let hash = crate::model::content_hash(&title, &desc, &tags);
+
+    Ok(Task {
+        id,
+        title,
+        desc,
+        status,
+        priority,
+        tags,
+        created_at,
+        updated_at: None,
+        completed_at,
+        dependencies: HashSet::new(),
+        time_entries: Vec::new(),
+        content_hash: hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task_with_tags(tags: &[&str]) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            title: "Buy groceries".to_string(),
+            desc: Some("milk, eggs; bread\nand butter".to_string()),
+            status: Status::Pending,
+            priority: 3,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            created_at: TimeStamp::now_utc(),
+            updated_at: None,
+            completed_at: None,
+            dependencies: HashSet::new(),
+            time_entries: Vec::new(),
+            content_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn round_trips_tags_containing_the_escaped_characters() {
+        let original = task_with_tags(&["errands", "foo,bar", "a;b", "multi\nline"]);
+
+        let ics = export_tasks(std::slice::from_ref(&original));
+        let imported = import_tasks(&ics).unwrap();
+
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].id, original.id);
+        assert_eq!(imported[0].title, original.title);
+        assert_eq!(imported[0].desc, original.desc);
+        assert_eq!(imported[0].tags, original.tags);
+    }
+
+    #[test]
+    fn round_trips_status_and_priority() {
+        let mut original = task_with_tags(&["work"]);
+        original.status = Status::Done;
+        original.priority = 5;
+        original.completed_at = Some(TimeStamp::now_utc());
+
+        let ics = export_tasks(std::slice::from_ref(&original));
+        let imported = import_tasks(&ics).unwrap();
+
+        assert_eq!(imported[0].status, Status::Done);
+        assert_eq!(imported[0].priority, 5);
+    }
+}
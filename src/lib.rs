@@ -1,27 +1,34 @@
+pub mod ical;
 pub mod model;
 pub mod storage;
 
 use anyhow::{Context, Result, bail};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use uuid::Uuid;
 
 use crate::{
     model::{Task, TodoFile},
-    storage::{atomic_write, load_from, open_or_init},
+    storage::{atomic_write, load_todo_file, open_or_init},
 };
 
-pub fn add_task(path: &str, task: Task) -> Result<()> {
+pub fn add_task(path: &str, task: Task, force: bool) -> Result<()> {
     // Open (or create) the storage file, grab exclusive lock.
     let file = open_or_init(path)?;
 
     // Deserialize current state. Passes &File as Read+Seek.
-    let mut data: TodoFile = load_from(&file)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
 
     if data.tasks.iter().any(|t| t.id == task.id) {
         bail!("task {} already exists", task.id);
     }
 
+    if !force {
+        if let Some(dup) = data.tasks.iter().find(|t| t.content_hash == task.content_hash) {
+            bail!("task duplicates existing task {} (use --force to add anyway)", dup.id);
+        }
+    }
+
     // Mutate in memory
     data.tasks.push(task);
 
@@ -30,15 +37,30 @@ pub fn add_task(path: &str, task: Task) -> Result<()> {
     atomic_write(path, &data).context("Writing tasks to disk")
 }
 
+/// Bail unless `id`'s dependencies are all `Done`. Shared by every path that
+/// can drive a task to `Status::Done`, so the gate can't be bypassed by
+/// reaching that state through a different verb.
+fn ensure_ready_for_done(data: &TodoFile, id: Uuid) -> Result<()> {
+    match data.tasks.iter().find(|t| t.id == id) {
+        Some(t) if !t.is_ready(&data.tasks) => {
+            bail!("task {id} is blocked on unfinished dependencies")
+        }
+        Some(_) => Ok(()),
+        None => bail!("task {id} not found"),
+    }
+}
+
 /// Mark task as done.
 pub fn complete_task(path: &str, id: Uuid) -> Result<()> {
     // Open and lock storage file.
     let file = open_or_init(path)?;
-    let mut data: TodoFile = load_from(&file)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
+
+    ensure_ready_for_done(&data, id)?;
 
     // Find task mutably in place.
     match data.tasks.iter_mut().find(|t| t.id == id) {
-        Some(t) => t.mark_done(),
+        Some(t) => t.transition(model::Status::Done)?,
         None => bail!("task {id} not found"),
     }
 
@@ -47,10 +69,38 @@ pub fn complete_task(path: &str, id: Uuid) -> Result<()> {
     atomic_write(path, &data).context("Writing updated task list.")
 }
 
+/// Move a task to a new state, enforcing [`model::Task::transition`]'s legal
+/// transition table.
+pub fn set_status(path: &str, id: Uuid, to: model::Status) -> Result<()> {
+    let file = open_or_init(path)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
+
+    if to == model::Status::Done {
+        ensure_ready_for_done(&data, id)?;
+    }
+
+    match data.tasks.iter_mut().find(|t| t.id == id) {
+        Some(t) => t.transition(to)?,
+        None => bail!("task {id} not found"),
+    }
+
+    drop(file);
+    atomic_write(path, &data).context("Persisting status change")
+}
+
+/// Drop `removed` from every remaining task's `dependencies`, so a deleted
+/// task can't leave a dangling id that `Task::is_ready` would otherwise
+/// treat as vacuously satisfied.
+fn scrub_dependency(tasks: &mut [Task], removed: Uuid) {
+    for t in tasks.iter_mut() {
+        t.dependencies.remove(&removed);
+    }
+}
+
 /// Remove a task (returns error if id is missing).
 pub fn remove_task(path: &str, id: Uuid) -> Result<()> {
     let file = open_or_init(path)?;
-    let mut data: TodoFile = load_from(&file)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
 
     // `retain` keeps all elements for which the predicate is *true*.
     let before = data.tasks.len();
@@ -61,24 +111,199 @@ pub fn remove_task(path: &str, id: Uuid) -> Result<()> {
         bail!("task {id} is not found");
     }
 
+    scrub_dependency(&mut data.tasks, id);
+
     drop(file);
     atomic_write(path, &data).context("Persisting after remove")
 }
 
+/// Record that task `id` depends on task `on` (i.e. `on` must finish first).
+/// Rejects the edge if either task is missing or if it would introduce a cycle.
+pub fn add_dependency(path: &str, id: Uuid, on: Uuid) -> Result<()> {
+    let file = open_or_init(path)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
+
+    if !data.tasks.iter().any(|t| t.id == id) {
+        bail!("task {id} not found");
+    }
+    if !data.tasks.iter().any(|t| t.id == on) {
+        bail!("task {on} not found");
+    }
+
+    if would_create_cycle(&data.tasks, id, on) {
+        bail!("adding dependency {id} -> {on} would create a cycle");
+    }
+
+    match data.tasks.iter_mut().find(|t| t.id == id) {
+        Some(t) => {
+            t.dependencies.insert(on);
+            t.updated_at = Some(model::TimeStamp::now_utc());
+        }
+        None => bail!("task {id} not found"),
+    }
+
+    drop(file);
+    atomic_write(path, &data).context("Persisting new dependency")
+}
+
+/// White/gray/black DFS coloring to detect whether adding the edge
+/// `from` -> `to` (meaning `from` depends on `to`) would create a cycle
+/// in the existing dependency graph.
+fn would_create_cycle(tasks: &[Task], from: Uuid, to: Uuid) -> bool {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Color {
+        White,
+        Gray,
+        Black,
+    }
+
+    fn visit(
+        node: Uuid,
+        tasks: &[Task],
+        extra_edge: (Uuid, Uuid),
+        colors: &mut HashMap<Uuid, Color>,
+    ) -> bool {
+        match colors.get(&node) {
+            Some(Color::Gray) => return true, // back edge: cycle
+            Some(Color::Black) => return false,
+            _ => {}
+        }
+        colors.insert(node, Color::Gray);
+
+        let mut deps: Vec<Uuid> = tasks
+            .iter()
+            .find(|t| t.id == node)
+            .map(|t| t.dependencies.iter().copied().collect())
+            .unwrap_or_default();
+        if node == extra_edge.0 {
+            deps.push(extra_edge.1);
+        }
+
+        if deps.iter().any(|&dep| visit(dep, tasks, extra_edge, colors)) {
+            return true;
+        }
+
+        colors.insert(node, Color::Black);
+        false
+    }
+
+    let mut colors: HashMap<Uuid, Color> = tasks.iter().map(|t| (t.id, Color::White)).collect();
+    visit(from, tasks, (from, to), &mut colors)
+}
+
+/// Topological order (prerequisites before dependents) via DFS postorder.
+/// If a cycle is somehow present, every task still appears exactly once, but
+/// within the cycle a dependency may come after its dependent.
+fn topo_order(tasks: &[Task]) -> Vec<Uuid> {
+    let mut order = Vec::with_capacity(tasks.len());
+    let mut visited: HashSet<Uuid> = HashSet::new();
+
+    fn visit(node: Uuid, tasks: &[Task], visited: &mut HashSet<Uuid>, order: &mut Vec<Uuid>) {
+        if !visited.insert(node) {
+            return;
+        }
+        if let Some(t) = tasks.iter().find(|t| t.id == node) {
+            for &dep in &t.dependencies {
+                visit(dep, tasks, visited, order);
+            }
+        }
+        order.push(node);
+    }
+
+    for t in tasks {
+        visit(t.id, tasks, &mut visited, &mut order);
+    }
+    order
+}
+
+/// Open a new time-tracking entry on a task.
+pub fn start_task(path: &str, id: Uuid) -> Result<()> {
+    let file = open_or_init(path)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
+
+    match data.tasks.iter_mut().find(|t| t.id == id) {
+        Some(t) => t.start()?,
+        None => bail!("task {id} not found"),
+    }
+
+    drop(file);
+    atomic_write(path, &data).context("Persisting started time entry")
+}
+
+/// Close the open time-tracking entry on a task.
+pub fn stop_task(path: &str, id: Uuid) -> Result<()> {
+    let file = open_or_init(path)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
+
+    match data.tasks.iter_mut().find(|t| t.id == id) {
+        Some(t) => t.stop()?,
+        None => bail!("task {id} not found"),
+    }
+
+    drop(file);
+    atomic_write(path, &data).context("Persisting stopped time entry")
+}
+
+/// Print the total tracked time for a task as hours/minutes.
+pub fn time_report(path: &str, id: Uuid) -> Result<()> {
+    let file = open_or_init(path)?;
+    let data: TodoFile = load_todo_file(path, &file)?;
+
+    let task = data
+        .tasks
+        .iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| anyhow::anyhow!("task {id} not found"))?;
+
+    let (hours, minutes) = hours_and_minutes(task.tracked_duration());
+    println!("{id}: {hours}h {minutes}m tracked");
+    Ok(())
+}
+
+fn hours_and_minutes(d: time::Duration) -> (i64, i64) {
+    let total_minutes = d.whole_minutes();
+    (total_minutes / 60, total_minutes % 60)
+}
+
 /// List tasks, optionally filtered out by priority and/or tags.
 /// Prints to stdout.
-pub fn list_tasks(path: &str, priority_filter: Option<u8>, tag_filter: &[String]) -> Result<()> {
+pub fn list_tasks(
+    path: &str,
+    priority_filter: Option<u8>,
+    tag_filter: &[String],
+    topo: bool,
+    total: bool,
+) -> Result<()> {
     // Pre-lowercase tag filter once, not per task.
     let needle: HashSet<String> = tag_filter.iter().map(|s| s.to_ascii_lowercase()).collect();
 
     let file = open_or_init(path)?;
-    let data: TodoFile = load_from(&file)?;
+    let data: TodoFile = load_todo_file(path, &file)?;
 
-    println!("ID                               | Pri | Status      | Title");
-    println!("----------------------------------+-----+-------------+----------------");
+    let ordered_ids = topo.then(|| topo_order(&data.tasks));
+    let rows: Vec<&Task> = match &ordered_ids {
+        Some(ids) => ids
+            .iter()
+            .filter_map(|id| data.tasks.iter().find(|t| &t.id == id))
+            .collect(),
+        None => data.tasks.iter().collect(),
+    };
 
-    data.tasks
-        .iter()
+    if total {
+        println!(
+            "ID                               | Pri | Status      | Ready   | Total   | Title"
+        );
+        println!(
+            "----------------------------------+-----+-------------+---------+---------+----------------"
+        );
+    } else {
+        println!("ID                               | Pri | Status      | Ready   | Title");
+        println!(
+            "----------------------------------+-----+-------------+---------+----------------"
+        );
+    }
+
+    rows.into_iter()
         .filter(|t| match priority_filter {
             Some(p) => t.priority == Some(p),
             None => true,
@@ -94,14 +319,219 @@ pub fn list_tasks(path: &str, priority_filter: Option<u8>, tag_filter: &[String]
             }
         })
         .for_each(|t| {
-            println!(
-                "{:<34} | {:<3} | {:<11} | {}",
-                t.id,
-                t.priority.map_or('-', |p| char::from(b'0' + p as u8)),
-                format!("{:?}", t.status).to_ascii_lowercase(),
-                t.title
-            );
+            let ready = if t.is_ready(&data.tasks) {
+                "ready"
+            } else {
+                "blocked"
+            };
+            if total {
+                let (hours, minutes) = hours_and_minutes(t.tracked_duration());
+                println!(
+                    "{:<34} | {:<3} | {:<11} | {:<7} | {:<7} | {}",
+                    t.id,
+                    t.priority.map_or('-', |p| char::from(b'0' + p as u8)),
+                    format!("{:?}", t.status).to_ascii_lowercase(),
+                    ready,
+                    format!("{hours}h{minutes}m"),
+                    t.title
+                );
+            } else {
+                println!(
+                    "{:<34} | {:<3} | {:<11} | {:<7} | {}",
+                    t.id,
+                    t.priority.map_or('-', |p| char::from(b'0' + p as u8)),
+                    format!("{:?}", t.status).to_ascii_lowercase(),
+                    ready,
+                    t.title
+                );
+            }
         });
 
     Ok(())
 }
+
+/// Report exact-duplicate tasks (same `content_hash`), or prune them,
+/// keeping the first-added task in each duplicate group.
+pub fn dedup_tasks(path: &str, prune: bool) -> Result<()> {
+    let file = open_or_init(path)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
+
+    let mut seen: HashMap<String, Uuid> = HashMap::new();
+    let mut duplicates: HashSet<Uuid> = HashSet::new();
+
+    for t in &data.tasks {
+        match seen.get(&t.content_hash) {
+            Some(original) => {
+                println!("duplicate: {} duplicates {original}", t.id);
+                duplicates.insert(t.id);
+            }
+            None => {
+                seen.insert(t.content_hash.clone(), t.id);
+            }
+        }
+    }
+
+    if duplicates.is_empty() {
+        println!("no duplicate tasks found");
+        return Ok(());
+    }
+
+    if prune {
+        data.tasks.retain(|t| !duplicates.contains(&t.id));
+        for &id in &duplicates {
+            scrub_dependency(&mut data.tasks, id);
+        }
+        drop(file);
+        atomic_write(path, &data).context("Persisting after dedup")?;
+        println!("removed {} duplicate task(s)", duplicates.len());
+    }
+
+    Ok(())
+}
+
+/// Export every task as an iCalendar (RFC 5545) VCALENDAR of VTODOs.
+pub fn export_ical(path: &str, out_path: &str) -> Result<()> {
+    let file = open_or_init(path)?;
+    let data: TodoFile = load_todo_file(path, &file)?;
+
+    std::fs::write(out_path, ical::export_tasks(&data.tasks))
+        .with_context(|| format!("writing {out_path}"))
+}
+
+/// Import VTODOs from an iCalendar file, merging them into the existing task list.
+/// A VTODO whose id (its `X-TODO-UUID`, else its `UID`) matches an existing task
+/// overwrites that task's iCal-owned fields in place (preserving its
+/// `dependencies`/`time_entries`); a COMPLETED VTODO over an unfinished task is
+/// rejected while that task's dependencies are unfinished. Otherwise a new task
+/// is added, unless it has the same `content_hash` as an existing task, in which
+/// case it is skipped.
+pub fn import_ical(path: &str, in_path: &str) -> Result<()> {
+    let ics = std::fs::read_to_string(in_path).with_context(|| format!("reading {in_path}"))?;
+    let imported = ical::import_tasks(&ics)?;
+
+    let file = open_or_init(path)?;
+    let mut data: TodoFile = load_todo_file(path, &file)?;
+
+    for task in imported {
+        match data.tasks.iter().position(|t| t.id == task.id) {
+            Some(i) => {
+                if task.status == model::Status::Done
+                    && data.tasks[i].status != model::Status::Done
+                {
+                    ensure_ready_for_done(&data, task.id)?;
+                }
+                data.tasks[i].merge_ical_fields(task);
+            }
+            None => {
+                if let Some(dup) = data.tasks.iter().find(|t| t.content_hash == task.content_hash) {
+                    println!("skipped duplicate import: {} duplicates existing task {}", task.title, dup.id);
+                    continue;
+                }
+                data.tasks.push(task);
+            }
+        }
+    }
+
+    drop(file);
+    atomic_write(path, &data).context("Persisting imported tasks")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task_with_deps(id: Uuid, deps: &[Uuid]) -> Task {
+        Task {
+            id,
+            title: "t".to_string(),
+            desc: None,
+            status: model::Status::Pending,
+            priority: 0,
+            tags: Vec::new(),
+            created_at: model::TimeStamp::now_utc(),
+            updated_at: None,
+            completed_at: None,
+            dependencies: deps.iter().copied().collect(),
+            time_entries: Vec::new(),
+            content_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn straight_line_has_no_cycle_and_sorts_prerequisites_first() {
+        let c = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let a = Uuid::new_v4();
+        // a depends on b, b depends on c.
+        let tasks = vec![
+            task_with_deps(a, &[b]),
+            task_with_deps(b, &[c]),
+            task_with_deps(c, &[]),
+        ];
+
+        assert!(!would_create_cycle(&tasks, a, c));
+
+        let order = topo_order(&tasks);
+        let pos = |id: Uuid| order.iter().position(|&x| x == id).unwrap();
+        assert!(pos(c) < pos(b));
+        assert!(pos(b) < pos(a));
+    }
+
+    #[test]
+    fn diamond_has_no_cycle_and_respects_dependencies() {
+        let d = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let c = Uuid::new_v4();
+        let a = Uuid::new_v4();
+        // a depends on b and c, both of which depend on d.
+        let tasks = vec![
+            task_with_deps(a, &[b, c]),
+            task_with_deps(b, &[d]),
+            task_with_deps(c, &[d]),
+            task_with_deps(d, &[]),
+        ];
+
+        assert!(!would_create_cycle(&tasks, a, d));
+
+        let order = topo_order(&tasks);
+        let pos = |id: Uuid| order.iter().position(|&x| x == id).unwrap();
+        assert!(pos(d) < pos(b));
+        assert!(pos(d) < pos(c));
+        assert!(pos(b) < pos(a));
+        assert!(pos(c) < pos(a));
+    }
+
+    #[test]
+    fn adding_reverse_edge_is_rejected_as_a_cycle() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        // a already depends on b; b -> a would close the loop.
+        let tasks = vec![task_with_deps(a, &[b]), task_with_deps(b, &[])];
+
+        assert!(would_create_cycle(&tasks, b, a));
+    }
+
+    #[test]
+    fn self_dependency_is_a_cycle() {
+        let a = Uuid::new_v4();
+        let tasks = vec![task_with_deps(a, &[])];
+
+        assert!(would_create_cycle(&tasks, a, a));
+    }
+
+    #[test]
+    fn scrub_dependency_clears_removed_id_everywhere() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let mut tasks = vec![task_with_deps(a, &[b]), task_with_deps(b, &[])];
+
+        // Before scrubbing, `a` is (correctly) not ready: `b` isn't Done.
+        assert!(!tasks[0].is_ready(&tasks));
+
+        scrub_dependency(&mut tasks, b);
+
+        assert!(tasks[0].dependencies.is_empty());
+        // After scrubbing the dangling id, `a` has no deps left and is ready.
+        assert!(tasks[0].is_ready(&tasks));
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 use clap::{Parser, Subcommand, ValueHint};
 use uuid::Uuid;
 
-use todo::model::Task;
+use todo::model::{Status, Task};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
@@ -19,6 +19,30 @@ struct Cli {
     output: String,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    Ical,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum StatusArg {
+    Pending,
+    InProgress,
+    Done,
+    Canceled,
+}
+
+impl From<StatusArg> for Status {
+    fn from(s: StatusArg) -> Self {
+        match s {
+            StatusArg::Pending => Status::Pending,
+            StatusArg::InProgress => Status::InProgress,
+            StatusArg::Done => Status::Done,
+            StatusArg::Canceled => Status::Canceled,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Verb {
     Add {
@@ -29,6 +53,10 @@ enum Verb {
 
         #[arg(long, value_delimiter = ',')]
         tags: Vec<String>,
+
+        /// Add even if an identical task (same title/desc/tags) already exists.
+        #[arg(long)]
+        force: bool,
     },
     Complete {
         #[arg(short = 'c', long)]
@@ -38,12 +66,72 @@ enum Verb {
         #[arg(short = 'r', long)]
         id: Uuid,
     },
+    /// Record that `--id` depends on `--on` finishing first.
+    Depend {
+        #[arg(long)]
+        id: Uuid,
+
+        #[arg(long)]
+        on: Uuid,
+    },
+    /// Open a time-tracking entry on a task.
+    Start {
+        #[arg(short = 'i', long)]
+        id: Uuid,
+    },
+    /// Close the open time-tracking entry on a task.
+    Stop {
+        #[arg(short = 'i', long)]
+        id: Uuid,
+    },
+    /// Report total tracked time for a task.
+    Time {
+        #[arg(short = 'i', long)]
+        id: Uuid,
+    },
+    /// Move a task to a new state (validated against the legal transition table).
+    Status {
+        #[arg(short = 'i', long)]
+        id: Uuid,
+
+        #[arg(long, value_enum)]
+        to: StatusArg,
+    },
+    /// Export the task list to an external format.
+    Export {
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: String,
+    },
+    /// Import tasks from an external format, merging into the task list.
+    Import {
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: String,
+    },
     List {
         #[arg(short = 'p', long)]
         priority: Option<u8>,
 
         #[arg(long, value_delimiter = ',')]
         tags: Vec<String>,
+
+        /// Emit tasks in dependency-topological order (prerequisites first).
+        #[arg(long)]
+        topo: bool,
+
+        /// Show accumulated tracked time per row.
+        #[arg(long)]
+        total: bool,
+    },
+    /// Report (or prune, with `--prune`) exact-duplicate tasks.
+    Dedup {
+        #[arg(long)]
+        prune: bool,
     },
 }
 
@@ -52,11 +140,14 @@ fn main() -> Result<()> {
     match cli.verb.unwrap_or(Verb::List {
         priority: None,
         tags: Vec::new(),
+        topo: false,
+        total: false,
     }) {
         Verb::Add {
             desc,
             priority,
             tags,
+            force,
         } => {
             let task = Task::builder()
                 .title(desc)
@@ -66,11 +157,30 @@ fn main() -> Result<()> {
                 .fold(Task::builder().title(&cli.title), |b, tag| b.tag(tag))
                 .build();
 
-            todo::add_task(&cli.output, task)?;
+            todo::add_task(&cli.output, task, force)?;
         }
         Verb::Complete { id } => todo::complete_task(&cli.output, id)?,
         Verb::Remove { id } => todo::remove_task(&cli.output, id)?,
-        Verb::List { priority, tags } => todo::list_tasks(&cli.output, priority, &tags)?,
+        Verb::Depend { id, on } => todo::add_dependency(&cli.output, id, on)?,
+        Verb::Start { id } => todo::start_task(&cli.output, id)?,
+        Verb::Stop { id } => todo::stop_task(&cli.output, id)?,
+        Verb::Time { id } => todo::time_report(&cli.output, id)?,
+        Verb::Status { id, to } => todo::set_status(&cli.output, id, to.into())?,
+        Verb::Export {
+            format: ExportFormat::Ical,
+            file,
+        } => todo::export_ical(&cli.output, &file)?,
+        Verb::Import {
+            format: ExportFormat::Ical,
+            file,
+        } => todo::import_ical(&cli.output, &file)?,
+        Verb::List {
+            priority,
+            tags,
+            topo,
+            total,
+        } => todo::list_tasks(&cli.output, priority, &tags, topo, total)?,
+        Verb::Dedup { prune } => todo::dedup_tasks(&cli.output, prune)?,
     }
     Ok(())
 }
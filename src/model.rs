@@ -1,11 +1,16 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use time::OffsetDateTime;
+use uuid::Uuid;
 
 // Self documenting alias
 pub type TimeStamp = OffsetDateTime;
 
 // --- Task Status ---
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Status {
     Done,
     Pending,
@@ -42,6 +47,45 @@ pub struct Task {
 
     /** When task reached a 'Done' state */
     pub completed_at: Option<TimeStamp>,
+
+    /** Other tasks that must reach `Status::Done` before this one can */
+    pub dependencies: HashSet<Uuid>,
+
+    /** Logged work sessions, most recent last */
+    pub time_entries: Vec<TimeEntry>,
+
+    /** SHA-256 of the normalized semantic fields, for duplicate detection */
+    pub content_hash: String,
+}
+
+/// Hash the fields that define a task's *meaning* (title, description, tags),
+/// normalized so whitespace/case/ordering don't produce different hashes for
+/// what is semantically the same task. Deliberately excludes `id` and timestamps.
+pub(crate) fn content_hash(title: &str, desc: &Option<String>, tags: &[String]) -> String {
+    let mut tags: Vec<String> = tags.iter().map(|t| t.trim().to_ascii_lowercase()).collect();
+    tags.sort();
+    tags.dedup();
+
+    let mut hasher = Sha256::new();
+    hasher.update(title.trim().to_ascii_lowercase().as_bytes());
+    hasher.update([0u8]); // field separator
+    hasher.update(
+        desc.as_deref()
+            .map(|d| d.trim().to_ascii_lowercase())
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    hasher.update([0u8]);
+    hasher.update(tags.join(",").as_bytes());
+
+    format!("{:x}", hasher.finalize())
+}
+
+// --- Time Tracking ---
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TimeEntry {
+    pub started_at: TimeStamp,
+    pub ended_at: Option<TimeStamp>,
 }
 
 // --- Zero size markers for the "typed-state" builder ---
@@ -74,14 +118,35 @@ impl Task {
         }
     }
 
-    /// Mark task as done
-    pub fn mark_done(&mut self) {
-        if self.status != Status::Done {
-            self.status = Status::Done;
-            let now = TimeStamp::now_utc();
+    /// Move to `to`, enforcing the legal transition table below and
+    /// centralizing the timestamp side effects of each state change:
+    ///
+    /// - `Pending`    -> `InProgress`, `Canceled`, `Done`
+    /// - `InProgress` -> `Done`, `Canceled`, `Pending`
+    /// - `Done`       -> `InProgress` (reopen)
+    /// - `Canceled`   -> `Pending`
+    pub fn transition(&mut self, to: Status) -> Result<()> {
+        let legal = match self.status {
+            Status::Pending => matches!(to, Status::InProgress | Status::Canceled | Status::Done),
+            Status::InProgress => {
+                matches!(to, Status::Done | Status::Canceled | Status::Pending)
+            }
+            Status::Done => matches!(to, Status::InProgress),
+            Status::Canceled => matches!(to, Status::Pending),
+        };
+        if !legal {
+            bail!("illegal transition from {:?} to {:?}", self.status, to);
+        }
+
+        let now = TimeStamp::now_utc();
+        if to == Status::Done {
             self.completed_at = Some(now);
-            self.updated_at = Some(now);
+        } else if self.status == Status::Done {
+            self.completed_at = None; // reopening clears completion
         }
+        self.status = to;
+        self.updated_at = Some(now);
+        Ok(())
     }
 
     /// Change priority & update timestamp
@@ -100,8 +165,78 @@ impl Task {
         let tag = t.into();
         if !self.tags.iter().any(|s| s.eq_ignore_ascii_case(&tag)) {
             self.tags.push(tag);
-            self.updated_at(Some(TimeStamp::now_utc));
+            self.content_hash = content_hash(&self.title, &self.desc, &self.tags);
+            self.updated_at = Some(TimeStamp::now_utc());
+        }
+    }
+
+    /// Open a new time-tracking entry. Bails if one is already open or the
+    /// task is already `Done`. Flips a `Pending` task to `InProgress`.
+    pub fn start(&mut self) -> Result<()> {
+        if self.status == Status::Done {
+            bail!("task is already done");
+        }
+        if self.time_entries.iter().any(|e| e.ended_at.is_none()) {
+            bail!("task already has an open time entry");
         }
+
+        let now = TimeStamp::now_utc();
+        self.time_entries.push(TimeEntry {
+            started_at: now,
+            ended_at: None,
+        });
+        if self.status == Status::Pending {
+            self.status = Status::InProgress;
+        }
+        self.updated_at = Some(now);
+        Ok(())
+    }
+
+    /// Close the currently open time-tracking entry. Bails if none is open.
+    pub fn stop(&mut self) -> Result<()> {
+        let now = TimeStamp::now_utc();
+        match self.time_entries.iter_mut().find(|e| e.ended_at.is_none()) {
+            Some(entry) => entry.ended_at = Some(now),
+            None => bail!("task has no open time entry"),
+        }
+        self.updated_at = Some(now);
+        Ok(())
+    }
+
+    /// Total tracked time across all *closed* entries.
+    pub fn tracked_duration(&self) -> time::Duration {
+        self.time_entries
+            .iter()
+            .filter_map(|e| e.ended_at.map(|end| end - e.started_at))
+            .fold(time::Duration::ZERO, |acc, d| acc + d)
+    }
+
+    /// Overwrite the fields an iCal VTODO owns (title, desc, status, priority,
+    /// tags, timestamps, content_hash) with `imported`'s, while preserving
+    /// this task's own `id`, `dependencies` and `time_entries` — fields a
+    /// calendar app knows nothing about.
+    pub(crate) fn merge_ical_fields(&mut self, imported: Task) {
+        self.title = imported.title;
+        self.desc = imported.desc;
+        self.status = imported.status;
+        self.priority = imported.priority;
+        self.tags = imported.tags;
+        self.created_at = imported.created_at;
+        self.updated_at = imported.updated_at;
+        self.completed_at = imported.completed_at;
+        self.content_hash = imported.content_hash;
+    }
+
+    /// Ready to work on: every dependency (if any) has reached `Status::Done`.
+    /// `tasks` is the full task list, used to resolve dependency ids to their status;
+    /// a dependency id not found in `tasks` counts as satisfied.
+    pub fn is_ready(&self, tasks: &[Task]) -> bool {
+        self.dependencies.iter().all(|dep| {
+            tasks
+                .iter()
+                .find(|t| &t.id == dep)
+                .is_none_or(|t| t.status == Status::Done)
+        })
     }
 }
 
@@ -146,19 +281,28 @@ impl TaskBuilder<HasTitle> {
     /// Consume builder and return fully-formed [`Task`]
     pub fn build(self) -> Task {
         let now = TimeStamp::now_utc();
+        let title = self.title.unwrap();
+        let hash = content_hash(&title, &self.desc, &self.tags);
         Task {
             id: Uuid::new_v4(),
-            title: self.title.unwrap(),
+            title,
             desc: self.desc,
             status: Status::Pending,
             tags: self.tags,
             created_at: now,
             updated_at: None,
             completed_at: None,
+            dependencies: HashSet::new(),
+            time_entries: Vec::new(),
+            content_hash: hash,
         }
     }
 }
 
+/// Current on-disk schema version. Bumped whenever `Task`/`Meta` gain or
+/// change fields; see `storage::load_todo_file` for the migration chain.
+pub const CURRENT_VERSION: u32 = 3;
+
 // --- File-level metadata ---
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Meta {
@@ -171,14 +315,14 @@ pub struct Meta {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TodoFile {
     meta: Meta,
-    tasks: Vec<Task>,
+    pub tasks: Vec<Task>,
 }
 
 impl TodoFile {
     pub fn new() -> Self {
         Self {
             meta: Meta {
-                version: 1,
+                version: CURRENT_VERSION,
                 current_id: 1,
                 generated_at: TimeStamp::now_utc(),
             },
@@ -186,3 +330,170 @@ impl TodoFile {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bare_task(status: Status) -> Task {
+        let mut task = Task::builder().title("t").build();
+        task.status = status;
+        task
+    }
+
+    #[test]
+    fn pending_may_move_to_in_progress_canceled_or_done() {
+        for to in [Status::InProgress, Status::Canceled, Status::Done] {
+            assert!(bare_task(Status::Pending).transition(to).is_ok());
+        }
+    }
+
+    #[test]
+    fn pending_to_pending_is_rejected() {
+        // Pending -> Pending isn't in the legal table (no-op transitions aren't special-cased).
+        assert!(bare_task(Status::Pending).transition(Status::Pending).is_err());
+    }
+
+    #[test]
+    fn in_progress_may_move_to_done_canceled_or_back_to_pending() {
+        for to in [Status::Done, Status::Canceled, Status::Pending] {
+            assert!(bare_task(Status::InProgress).transition(to).is_ok());
+        }
+    }
+
+    #[test]
+    fn done_may_only_reopen_to_in_progress() {
+        assert!(bare_task(Status::Done).transition(Status::InProgress).is_ok());
+        assert!(bare_task(Status::Done).transition(Status::Pending).is_err());
+        assert!(bare_task(Status::Done).transition(Status::Canceled).is_err());
+    }
+
+    #[test]
+    fn canceled_may_only_return_to_pending() {
+        assert!(bare_task(Status::Canceled).transition(Status::Pending).is_ok());
+        assert!(bare_task(Status::Canceled).transition(Status::InProgress).is_err());
+        assert!(bare_task(Status::Canceled).transition(Status::Done).is_err());
+    }
+
+    #[test]
+    fn transitioning_to_done_sets_completed_at_and_updated_at() {
+        let mut task = bare_task(Status::Pending);
+        assert!(task.completed_at.is_none());
+
+        task.transition(Status::Done).unwrap();
+
+        assert_eq!(task.status, Status::Done);
+        assert!(task.completed_at.is_some());
+        assert!(task.updated_at.is_some());
+    }
+
+    #[test]
+    fn reopening_from_done_clears_completed_at_and_bumps_updated_at() {
+        let mut task = bare_task(Status::Pending);
+        task.transition(Status::Done).unwrap();
+        assert!(task.completed_at.is_some());
+
+        task.transition(Status::InProgress).unwrap();
+
+        assert_eq!(task.status, Status::InProgress);
+        assert!(task.completed_at.is_none());
+        assert!(task.updated_at.is_some());
+    }
+
+    #[test]
+    fn illegal_transition_names_both_states_in_the_error() {
+        let mut task = bare_task(Status::Done);
+        let err = task.transition(Status::Canceled).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("Done"));
+        assert!(msg.contains("Canceled"));
+    }
+
+    #[test]
+    fn start_flips_pending_to_in_progress() {
+        let mut task = bare_task(Status::Pending);
+        task.start().unwrap();
+        assert_eq!(task.status, Status::InProgress);
+        assert_eq!(task.time_entries.len(), 1);
+        assert!(task.time_entries[0].ended_at.is_none());
+    }
+
+    #[test]
+    fn start_rejects_a_second_open_entry() {
+        let mut task = bare_task(Status::Pending);
+        task.start().unwrap();
+        assert!(task.start().is_err());
+        assert_eq!(task.time_entries.len(), 1);
+    }
+
+    #[test]
+    fn start_rejects_an_already_done_task() {
+        let mut task = bare_task(Status::Done);
+        assert!(task.start().is_err());
+        assert!(task.time_entries.is_empty());
+    }
+
+    #[test]
+    fn stop_rejects_when_nothing_is_open() {
+        let mut task = bare_task(Status::Pending);
+        assert!(task.stop().is_err());
+    }
+
+    #[test]
+    fn stop_closes_the_open_entry() {
+        let mut task = bare_task(Status::Pending);
+        task.start().unwrap();
+        task.stop().unwrap();
+        assert!(task.time_entries[0].ended_at.is_some());
+        // No open entry left, so a second stop is rejected.
+        assert!(task.stop().is_err());
+    }
+
+    #[test]
+    fn tracked_duration_sums_only_closed_entries() {
+        let mut task = bare_task(Status::Pending);
+        let start = TimeStamp::now_utc();
+
+        task.time_entries.push(TimeEntry {
+            started_at: start,
+            ended_at: Some(start + time::Duration::minutes(30)),
+        });
+        task.time_entries.push(TimeEntry {
+            started_at: start,
+            ended_at: Some(start + time::Duration::minutes(15)),
+        });
+        // Still-open entry must not contribute to the total.
+        task.time_entries.push(TimeEntry {
+            started_at: start,
+            ended_at: None,
+        });
+
+        assert_eq!(task.tracked_duration(), time::Duration::minutes(45));
+    }
+
+    #[test]
+    fn add_tag_refreshes_content_hash() {
+        let mut task = Task::builder().title("t").build();
+        let before = task.content_hash.clone();
+
+        task.add_tag("urgent");
+
+        assert_ne!(task.content_hash, before);
+        assert_eq!(
+            task.content_hash,
+            content_hash(&task.title, &task.desc, &task.tags)
+        );
+    }
+
+    #[test]
+    fn add_tag_is_a_noop_for_a_case_insensitive_duplicate() {
+        let mut task = Task::builder().title("t").build();
+        task.add_tag("urgent");
+        let after_first = task.content_hash.clone();
+
+        task.add_tag("URGENT");
+
+        assert_eq!(task.content_hash, after_first);
+        assert_eq!(task.tags, vec!["urgent".to_string()]);
+    }
+}
@@ -6,36 +6,13 @@ use std::{
     path::Path,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use fs4::FileExt;
 use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
 use tempfile::NamedTempFile; // For atomic writes
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Meta {
-    version: u32,
-    current_id: u32,
-    generated_at: TimeStamp,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-pub struct TodoFile {
-    meta: Meta,
-    tasks: Vec<Task>,
-}
-
-impl TodoFile {
-    pub fn new() -> Self {
-        Self {
-            meta: Meta {
-                version: 1,
-                current_id: 1,
-                generated_at: TimeStamp::now_utc(),
-            },
-            tasks: Vec::new(),
-        }
-    }
-}
+use crate::model::{CURRENT_VERSION, TodoFile};
 
 /// Open storage file *with* a shared lock (read/write),
 /// auto-creating and seeding if missing.
@@ -113,9 +90,210 @@ where
     Ok(data)
 }
 
+/// Read the current `todo.json`, running it through any pending schema
+/// migrations before deserializing into [`TodoFile`]. If a migration ran,
+/// the upgraded schema is rewritten to `path` atomically so later loads skip it.
+pub fn load_todo_file(path: impl AsRef<Path>, file: &File) -> Result<TodoFile> {
+    let path = path.as_ref();
+    let mut raw: File = file.try_clone().context("cloning file handle")?;
+    raw.seek(SeekFrom::Start(0))?;
+
+    let mut value: serde_json::Value =
+        serde_json::from_reader(&raw).context("JSON parse")?;
+
+    let migrated = migrate_to_current(&mut value)?;
+
+    let data: TodoFile =
+        serde_json::from_value(value).context("deserializing migrated todo file")?;
+
+    if migrated {
+        atomic_write(path, &data).context("persisting migrated schema")?;
+    }
+
+    Ok(data)
+}
+
+/// Run `value` through every migration between its `meta.version` and
+/// [`CURRENT_VERSION`], mutating it in place. Returns whether any migration ran.
+fn migrate_to_current(value: &mut serde_json::Value) -> Result<bool> {
+    let mut migrated = false;
+
+    loop {
+        let version = value
+            .get("meta")
+            .and_then(|m| m.get("version"))
+            .and_then(|v| v.as_u64())
+            .context("reading meta.version")?;
+
+        if version > CURRENT_VERSION as u64 {
+            bail!(
+                "todo.json is at schema version {version}, but this binary only understands up to {CURRENT_VERSION}"
+            );
+        }
+        if version == CURRENT_VERSION as u64 {
+            break;
+        }
+
+        match version {
+            1 => migrate_v1_to_v2(value)?,
+            2 => migrate_v2_to_v3(value)?,
+            other => bail!("no migration registered from schema version {other}"),
+        }
+        migrated = true;
+    }
+
+    Ok(migrated)
+}
+
+/// v1 -> v2: tasks gained `dependencies` and `time_entries`; default both to empty.
+fn migrate_v1_to_v2(value: &mut serde_json::Value) -> Result<()> {
+    if let Some(tasks) = value.get_mut("tasks").and_then(|t| t.as_array_mut()) {
+        for task in tasks {
+            let obj = task
+                .as_object_mut()
+                .context("task entry is not a JSON object")?;
+            obj.entry("dependencies").or_insert_with(|| json!([]));
+            obj.entry("time_entries").or_insert_with(|| json!([]));
+        }
+    }
+    value["meta"]["version"] = json!(2);
+    Ok(())
+}
+
+/// v2 -> v3: tasks gained `content_hash`; backfill it from the existing
+/// title/desc/tags using the same normalization `TaskBuilder::build` applies.
+fn migrate_v2_to_v3(value: &mut serde_json::Value) -> Result<()> {
+    if let Some(tasks) = value.get_mut("tasks").and_then(|t| t.as_array_mut()) {
+        for task in tasks {
+            let obj = task
+                .as_object_mut()
+                .context("task entry is not a JSON object")?;
+            if obj.contains_key("content_hash") {
+                continue;
+            }
+
+            let title = obj.get("title").and_then(|v| v.as_str()).unwrap_or("");
+            let desc = obj
+                .get("desc")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            let tags: Vec<String> = obj
+                .get("tags")
+                .and_then(|v| v.as_array())
+                .map(|a| {
+                    a.iter()
+                        .filter_map(|t| t.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let hash = crate::model::content_hash(title, &desc, &tags);
+            obj.insert("content_hash".to_string(), json!(hash));
+        }
+    }
+    value["meta"]["version"] = json!(3);
+    Ok(())
+}
+
 // --- Internal Helper: advisory locking ---
 fn lock_file(file: &File) -> Result<()> {
     file.lock_exclusive()
         .with_context("Another process is already using the todo file.")?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v1_fixture() -> serde_json::Value {
+        json!({
+            "meta": { "version": 1, "current_id": 1, "generated_at": "2024-01-01T00:00:00Z" },
+            "tasks": [
+                { "id": "5b1f3a1a-6f2e-4e2a-9a1a-000000000001",
+                  "title": "Write report",
+                  "desc": "Quarterly numbers",
+                  "status": "Pending",
+                  "priority": 2,
+                  "tags": ["work", "Q1"],
+                  "created_at": "2024-01-01T00:00:00Z",
+                  "updated_at": null,
+                  "completed_at": null }
+            ]
+        })
+    }
+
+    #[test]
+    fn migrate_v1_to_v2_backfills_dependencies_and_time_entries() {
+        let mut value = v1_fixture();
+        migrate_v1_to_v2(&mut value).unwrap();
+
+        assert_eq!(value["meta"]["version"], json!(2));
+        let task = &value["tasks"][0];
+        assert_eq!(task["dependencies"], json!([]));
+        assert_eq!(task["time_entries"], json!([]));
+    }
+
+    #[test]
+    fn migrate_v2_to_v3_backfills_content_hash() {
+        let mut value = v1_fixture();
+        migrate_v1_to_v2(&mut value).unwrap();
+        migrate_v2_to_v3(&mut value).unwrap();
+
+        assert_eq!(value["meta"]["version"], json!(3));
+        let hash = value["tasks"][0]["content_hash"]
+            .as_str()
+            .expect("content_hash should be a string")
+            .to_string();
+        assert!(!hash.is_empty());
+
+        // Matches what `model::content_hash` would compute directly from the fixture's fields.
+        let expected = crate::model::content_hash(
+            "Write report",
+            &Some("Quarterly numbers".to_string()),
+            &["work".to_string(), "Q1".to_string()],
+        );
+        assert_eq!(hash, expected);
+    }
+
+    #[test]
+    fn migrate_v2_to_v3_leaves_an_existing_content_hash_untouched() {
+        let mut value = v1_fixture();
+        migrate_v1_to_v2(&mut value).unwrap();
+        value["tasks"][0]["content_hash"] = json!("already-set");
+
+        migrate_v2_to_v3(&mut value).unwrap();
+
+        assert_eq!(value["tasks"][0]["content_hash"], json!("already-set"));
+    }
+
+    #[test]
+    fn migrate_to_current_runs_the_full_chain_from_v1() {
+        let mut value = v1_fixture();
+        let migrated = migrate_to_current(&mut value).unwrap();
+
+        assert!(migrated);
+        assert_eq!(value["meta"]["version"], json!(CURRENT_VERSION));
+        assert_eq!(value["tasks"][0]["dependencies"], json!([]));
+        assert_eq!(value["tasks"][0]["time_entries"], json!([]));
+        assert!(value["tasks"][0]["content_hash"].is_string());
+    }
+
+    #[test]
+    fn migrate_to_current_is_a_noop_already_at_current_version() {
+        let mut value = v1_fixture();
+        migrate_to_current(&mut value).unwrap();
+
+        let migrated_again = migrate_to_current(&mut value).unwrap();
+        assert!(!migrated_again);
+    }
+
+    #[test]
+    fn migrate_to_current_rejects_a_file_newer_than_this_binary() {
+        let mut value = v1_fixture();
+        value["meta"]["version"] = json!(CURRENT_VERSION + 1);
+
+        let err = migrate_to_current(&mut value).unwrap_err();
+        assert!(err.to_string().contains("only understands up to"));
+    }
+}